@@ -0,0 +1,228 @@
+//! A CLAP/VST3 instrument wrapping `padme_core::System`, driving its
+//! emulated APU via a blank, running Game Boy ROM. No window or link cable
+//! in this mode, so `Screen`/`SerialOutput` are no-ops; generated stereo
+//! samples are resampled from `AUDIO_SAMPLE_RATE` to the host's rate and
+//! written straight into its buffer, instead of going through
+//! `AudioPlayer`'s cpal stream.
+//!
+//! MIDI note on/off events are received but currently have no audible
+//! effect: `padme_core::System` only exposes `set_button`, `update_frame`,
+//! `screen`/`serial`/`speaker` and ROM (re)loading — there is no way to
+//! write to the APU's registers (or the bus generally) from outside the
+//! ROM's own running code. Turning notes into sound needs either an
+//! upstream `padme-core` API to poke bus addresses, or a driver ROM that
+//! reacts to `set_button` presses by writing its own APU registers; neither
+//! exists yet, so this plugin only passes through whatever the embedded
+//! ROM's code produces on its own (silence, since it never touches the APU).
+
+use std::sync::{Arc, Mutex};
+
+use nih_plug::prelude::*;
+use padme_core::{AudioSpeaker, Pixel, Rom, Screen, SerialOutput, System, AUDIO_SAMPLE_RATE};
+
+mod ring_buffer;
+
+use ring_buffer::RingBuffer;
+
+/// Default/minimum capacity of the ring buffer, in `AUDIO_SAMPLE_RATE`
+/// stereo frames; `initialize` resizes it to fit the host's actual block
+/// size and sample rate once they're known.
+const RING_FRAMES: usize = 4096;
+
+/// Pushes samples generated by `System::update_frame` into the ring that
+/// `process` resamples from, instead of `AudioPlayer`'s buffer.
+struct RingSpeaker {
+    ring: Arc<Mutex<RingBuffer>>,
+}
+
+impl AudioSpeaker for RingSpeaker {
+    fn set_samples(&mut self, left: f32, right: f32) {
+        self.ring.lock().unwrap().push(left, right);
+    }
+}
+
+/// No window in plugin mode: the Game Boy LCD is never displayed.
+struct NullScreen;
+
+impl Screen for NullScreen {
+    fn set_pixel(&mut self, _px: &Pixel, _x: u8, _y: u8) {}
+    fn update(&mut self) {}
+}
+
+/// No link cable in plugin mode: serial output is discarded.
+struct NullSerial;
+
+impl SerialOutput for NullSerial {
+    fn putchar(&mut self, _c: u8) {}
+}
+
+/// Header offsets relevant to building a minimal, valid GB ROM in memory.
+const HEADER_CHECKSUM_START: usize = 0x134;
+const HEADER_CHECKSUM_END: usize = 0x14D;
+const HEADER_CHECKSUM: usize = 0x14D;
+const CARTRIDGE_TYPE: usize = 0x147;
+const ROM_SIZE: usize = 0x148;
+const RAM_SIZE: usize = 0x149;
+
+/// Builds a minimal, valid 32KB no-MBC GB ROM in memory, since the plugin
+/// has no ROM file to load: header fields are zeroed (ROM only, no
+/// banking, no RAM) and the header checksum is computed so `Rom::load`
+/// accepts it. The cartridge is otherwise blank.
+fn embedded_rom() -> Vec<u8> {
+    let mut rom = vec![0u8; 0x8000];
+
+    rom[CARTRIDGE_TYPE] = 0x00;
+    rom[ROM_SIZE] = 0x00;
+    rom[RAM_SIZE] = 0x00;
+
+    let mut checksum: u8 = 0;
+    for byte in &rom[HEADER_CHECKSUM_START..HEADER_CHECKSUM_END] {
+        checksum = checksum.wrapping_sub(*byte).wrapping_sub(1);
+    }
+    rom[HEADER_CHECKSUM] = checksum;
+
+    rom
+}
+
+#[derive(Params)]
+struct PadmeSynthParams {}
+
+impl Default for PadmeSynthParams {
+    fn default() -> Self {
+        Self {}
+    }
+}
+
+pub struct PadmeSynth {
+    params: Arc<PadmeSynthParams>,
+    system: System<NullScreen, NullSerial, RingSpeaker>,
+    ring: Arc<Mutex<RingBuffer>>,
+    /// Fractional read cursor into `ring`, expressed in source
+    /// (`AUDIO_SAMPLE_RATE`) frames; see `play_frame` in `src/main.rs` for
+    /// the same linear-interpolation technique.
+    pos: f64,
+    /// `AUDIO_SAMPLE_RATE / host sample rate`, set from `initialize`'s
+    /// `BufferConfig` once the host's actual rate is known.
+    ratio: f64,
+}
+
+impl Default for PadmeSynth {
+    fn default() -> Self {
+        let ring = Arc::new(Mutex::new(RingBuffer::new(RING_FRAMES)));
+        let rom = Rom::load(embedded_rom()).expect("embedded ROM failed to load");
+        let system = System::new(rom, NullScreen, NullSerial, RingSpeaker { ring: ring.clone() });
+
+        Self {
+            params: Arc::new(PadmeSynthParams::default()),
+            system,
+            ring,
+            pos: 0.0,
+            ratio: 1.0,
+        }
+    }
+}
+
+impl Plugin for PadmeSynth {
+    const NAME: &'static str = "Padme";
+    const VENDOR: &'static str = "padme-demo";
+    const URL: &'static str = env!("CARGO_PKG_HOMEPAGE");
+    const EMAIL: &'static str = "info@example.com";
+    const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[AudioIOLayout {
+        main_input_channels: None,
+        main_output_channels: NonZeroU32::new(2),
+        ..AudioIOLayout::const_default()
+    }];
+
+    const MIDI_INPUT: MidiConfig = MidiConfig::Basic;
+
+    type SysExMessage = ();
+    type BackgroundTask = ();
+
+    fn params(&self) -> Arc<dyn Params> {
+        self.params.clone()
+    }
+
+    fn initialize(
+        &mut self,
+        _audio_io_layout: &AudioIOLayout,
+        buffer_config: &BufferConfig,
+        _context: &mut impl InitContext<Self>,
+    ) -> bool {
+        self.ratio = AUDIO_SAMPLE_RATE as f64 / buffer_config.sample_rate as f64;
+        self.pos = 0.0;
+
+        // Must hold at least one block's worth of resampled source frames,
+        // or `process` would spin forever waiting for frames the ring has
+        // already overwritten.
+        let capacity = ((buffer_config.max_buffer_size as f64 * self.ratio).ceil() as usize + 1)
+            .max(RING_FRAMES);
+        self.ring.lock().unwrap().resize(capacity);
+
+        true
+    }
+
+    fn process(
+        &mut self,
+        buffer: &mut Buffer,
+        _aux: &mut AuxiliaryBuffers,
+        context: &mut impl ProcessContext<Self>,
+    ) -> ProcessStatus {
+        let channels = buffer.as_slice();
+        assert_eq!(channels.len(), 2, "padme only supports stereo output");
+
+        // Drained for the host's benefit (and to leave a hook for the day
+        // notes can actually reach the APU); see the module doc for why
+        // they don't produce sound yet.
+        while context.next_event().is_some() {}
+
+        let block_len = channels[0].len();
+
+        // Step the emulator until the ring holds enough AUDIO_SAMPLE_RATE
+        // frames to resample this whole block to the host's rate.
+        let needed = (block_len as f64 * self.ratio).ceil() as usize + 1;
+        while self.ring.lock().unwrap().len() < needed {
+            self.system.update_frame();
+        }
+
+        // Resample into the host's buffer via linear interpolation, same
+        // technique as `play_frame` in `src/main.rs`.
+        let mut ring = self.ring.lock().unwrap();
+        for frame in 0..block_len {
+            let idx = self.pos.floor() as usize;
+            let frac = self.pos.fract() as f32;
+
+            let (l0, r0) = ring.peek(idx);
+            let (l1, r1) = ring.peek(idx + 1);
+            channels[0][frame] = l0 + (l1 - l0) * frac;
+            channels[1][frame] = r0 + (r1 - r0) * frac;
+
+            self.pos += self.ratio;
+        }
+
+        let consumed = self.pos.floor() as usize;
+        if consumed > 0 {
+            ring.advance(consumed);
+            self.pos -= consumed as f64;
+        }
+
+        ProcessStatus::Normal
+    }
+}
+
+impl ClapPlugin for PadmeSynth {
+    const CLAP_ID: &'static str = "com.padme-demo.padme";
+    const CLAP_DESCRIPTION: Option<&'static str> = Some("Game Boy APU synth");
+    const CLAP_MANUAL_URL: Option<&'static str> = Some(Self::URL);
+    const CLAP_SUPPORT_URL: Option<&'static str> = None;
+    const CLAP_FEATURES: &'static [ClapFeature] = &[ClapFeature::Instrument, ClapFeature::Synthesizer];
+}
+
+impl Vst3Plugin for PadmeSynth {
+    const VST3_CLASS_ID: [u8; 16] = *b"PadmeDemoSynth01";
+    const VST3_SUBCATEGORIES: &'static [Vst3SubCategory] = &[Vst3SubCategory::Instrument, Vst3SubCategory::Synth];
+}
+
+nih_export_clap!(PadmeSynth);
+nih_export_vst3!(PadmeSynth);
@@ -0,0 +1,114 @@
+//! Gamepad input via gilrs, merged with the minifb keyboard reads each
+//! frame. Controllers are hot-pluggable: `poll` drains gilrs' connect/
+//! disconnect/button/axis event queue before reading the current state of
+//! every connected gamepad.
+
+use log::warn;
+
+use gilrs::{Axis, Button as GilrsButton, Gilrs};
+
+/// Deadzone past which an analog stick axis counts as a digital direction
+/// press, roughly the 0x4000 extent of a 16-bit stick axis's half-range.
+const STICK_DEADZONE: f32 = 0x4000 as f32 / i16::MAX as f32;
+
+/// Which physical gamepad button drives each Game Boy button. Users can
+/// rebind a controller by constructing a `GamepadMapping` other than
+/// `default()`.
+pub struct GamepadMapping {
+    pub a: GilrsButton,
+    pub b: GilrsButton,
+    pub start: GilrsButton,
+    pub select: GilrsButton,
+    pub up: GilrsButton,
+    pub down: GilrsButton,
+    pub left: GilrsButton,
+    pub right: GilrsButton,
+}
+
+impl Default for GamepadMapping {
+    fn default() -> Self {
+        Self {
+            a: GilrsButton::South,
+            b: GilrsButton::East,
+            start: GilrsButton::Start,
+            select: GilrsButton::Select,
+            up: GilrsButton::DPadUp,
+            down: GilrsButton::DPadDown,
+            left: GilrsButton::DPadLeft,
+            right: GilrsButton::DPadRight,
+        }
+    }
+}
+
+/// Pressed state for each Game Boy button, OR'd across every connected
+/// gamepad; merge this with the keyboard reads before calling
+/// `emu.set_button(...)`.
+#[derive(Default)]
+pub struct ButtonState {
+    pub a: bool,
+    pub b: bool,
+    pub start: bool,
+    pub select: bool,
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+}
+
+pub struct GamepadInput {
+    /// `None` when the gilrs backend failed to initialize; gamepad support
+    /// is additive to the keyboard, so this falls back to keyboard-only
+    /// input instead of refusing to start.
+    gilrs: Option<Gilrs>,
+    mapping: GamepadMapping,
+}
+
+impl GamepadInput {
+    pub fn new(mapping: GamepadMapping) -> Self {
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(err) => {
+                warn!("gamepad support disabled, failed to initialize gilrs: {}", err);
+                None
+            }
+        };
+
+        Self { gilrs, mapping }
+    }
+
+    /// Drains pending connect/disconnect/button/axis events, then returns
+    /// the current combined button state across every connected gamepad.
+    /// Returns the default (all released) state when gilrs isn't available.
+    pub fn poll(&mut self) -> ButtonState {
+        let Some(gilrs) = &mut self.gilrs else {
+            return ButtonState::default();
+        };
+
+        while gilrs.next_event().is_some() {}
+
+        let mut state = ButtonState::default();
+
+        for (_, gamepad) in gilrs.gamepads() {
+            state.a |= gamepad.is_pressed(self.mapping.a);
+            state.b |= gamepad.is_pressed(self.mapping.b);
+            state.start |= gamepad.is_pressed(self.mapping.start);
+            state.select |= gamepad.is_pressed(self.mapping.select);
+            state.up |= gamepad.is_pressed(self.mapping.up);
+            state.down |= gamepad.is_pressed(self.mapping.down);
+            state.left |= gamepad.is_pressed(self.mapping.left);
+            state.right |= gamepad.is_pressed(self.mapping.right);
+
+            if let (Some(x), Some(y)) = (gamepad.axis_data(Axis::LeftStickX), gamepad.axis_data(Axis::LeftStickY)) {
+                let x = x.value();
+                let y = y.value();
+
+                state.right |= x > STICK_DEADZONE;
+                state.left |= x < -STICK_DEADZONE;
+                state.up |= y > STICK_DEADZONE;
+                state.down |= y < -STICK_DEADZONE;
+            }
+        }
+
+        state
+    }
+}
@@ -11,35 +11,188 @@ use log::*;
 use minifb::{Key, Window, WindowOptions, Scale};
 use padme_core::{FRAME_HEIGHT, FRAME_WIDTH, Button, Rom, System, Pixel, Screen, SerialOutput, AudioSpeaker, AUDIO_SAMPLE_RATE};
 
-fn play_frame<T: cpal::Sample>(outbuffer: &mut[T], sample_buf: &Arc<Mutex<Vec<f32>>>) {
-    let mut sample_buf = sample_buf.lock().unwrap();
-    let min = std::cmp::min(outbuffer.len(), sample_buf.len());
+mod ring_buffer;
+mod gamepad;
 
-    for (i, s) in sample_buf.drain(..min).enumerate() {
-        outbuffer[i] = cpal::Sample::from(&s);
+use ring_buffer::RingBuffer;
+
+/// Number of device callback-lengths the ring buffer is sized to hold.
+/// Bounds latency while leaving enough slack that emulator/callback jitter
+/// doesn't underrun the buffer.
+const RING_CALLBACKS: usize = 6;
+
+/// Plays back `outbuffer.len() / channels` output frames, resampling the
+/// `AUDIO_SAMPLE_RATE` interleaved stereo frames in `ring` to the device's
+/// actual rate/channel count via linear interpolation.
+///
+/// `pos` is the fractional read cursor into `ring`, expressed in source
+/// frames; it is carried across calls so the interpolation stays
+/// continuous even though each callback only sees a slice of the stream.
+fn play_frame<T: cpal::Sample>(
+    outbuffer: &mut [T],
+    ring: &Arc<Mutex<RingBuffer>>,
+    channels: usize,
+    ratio: f64,
+    pos: &mut f64,
+) {
+    let mut ring = ring.lock().unwrap();
+
+    if ring.len() == 0 {
+        trace!("audio ring underrun, repeating last frame");
+    }
+
+    for frame in outbuffer.chunks_mut(channels) {
+        let idx = pos.floor() as usize;
+        let frac = pos.fract() as f32;
+
+        let (l0, r0) = ring.peek(idx);
+        let (l1, r1) = ring.peek(idx + 1);
+        let left = l0 + (l1 - l0) * frac;
+        let right = r0 + (r1 - r0) * frac;
+
+        match channels {
+            1 => frame[0] = cpal::Sample::from(&((left + right) * 0.5)),
+            _ => {
+                frame[0] = cpal::Sample::from(&left);
+                for ch in frame.iter_mut().skip(1) {
+                    *ch = cpal::Sample::from(&right);
+                }
+            }
+        }
+
+        *pos += ratio;
+    }
+
+    let consumed = pos.floor() as usize;
+    if consumed > 0 {
+        ring.advance(consumed);
+        *pos -= consumed as f64;
     }
 }
 
-fn create_stream(sample_buf: &Arc<Mutex<Vec<f32>>>) -> cpal::Stream {
-    let host = cpal::default_host();
-    let device = host.default_output_device().unwrap();
+/// Picks the device's stereo/`AUDIO_SAMPLE_RATE`/`wanted_format` config when
+/// available, falling back to its default config (any rate/format/channel
+/// count) otherwise; `play_frame`'s resampler bridges the difference.
+fn select_config(device: &cpal::Device, wanted_format: Option<cpal::SampleFormat>) -> cpal::SupportedStreamConfig {
     let sample_rate = cpal::SampleRate(AUDIO_SAMPLE_RATE);
+    let wanted_format = wanted_format.unwrap_or(cpal::SampleFormat::F32);
     let mut supported_configs = device.supported_output_configs().unwrap();
-    // Find a config that supports:
-    // - stereo
-    // - float 32
-    // - sample rate = 48kHz
-    let supported_config = supported_configs.find(| cnf | cnf.channels() == 2
-                                                  && sample_rate >= cnf.min_sample_rate()
-                                                  && sample_rate <= cnf.max_sample_rate()
-                                                  && cnf.sample_format() == cpal::SampleFormat::F32).unwrap();
-    let supported_config = supported_config.with_sample_rate(sample_rate);
-    let sample_buf = sample_buf.clone();
-    let stream = device.build_output_stream(
-        &supported_config.config(),
-        move |data: &mut [f32], _: &cpal::OutputCallbackInfo| play_frame(data, &sample_buf),
+
+    if let Some(cnf) = supported_configs.find(|cnf| cnf.channels() == 2
+                                               && sample_rate >= cnf.min_sample_rate()
+                                               && sample_rate <= cnf.max_sample_rate()
+                                               && cnf.sample_format() == wanted_format) {
+        return cnf.with_sample_rate(sample_rate);
+    }
+
+    warn!("device has no stereo/{}Hz/{:?} config, falling back to its default config", AUDIO_SAMPLE_RATE, wanted_format);
+    device.default_output_config().expect("device has no output config at all")
+}
+
+/// Resolves `--host` (matched against the names printed by
+/// `--list-devices`) to a concrete host, or the default host when unset.
+fn find_host(selector: &Option<String>) -> cpal::Host {
+    match selector {
+        None => cpal::default_host(),
+        Some(sel) => {
+            let host_id = cpal::available_hosts()
+                .into_iter()
+                .find(|id| id.name() == sel)
+                .unwrap_or_else(|| panic!("no audio host named '{}'", sel));
+            cpal::host_from_id(host_id).unwrap()
+        }
+    }
+}
+
+/// Resolves `--device` (a name or an index into `host.output_devices()`) to
+/// a concrete device, or the host's default output device when unset.
+fn find_device(host: &cpal::Host, selector: &Option<String>) -> cpal::Device {
+    match selector {
+        None => host.default_output_device().expect("no default output device"),
+        Some(sel) => {
+            if let Ok(index) = sel.parse::<usize>() {
+                host.output_devices()
+                    .unwrap()
+                    .nth(index)
+                    .unwrap_or_else(|| panic!("no output device at index {}", index))
+            } else {
+                host.output_devices()
+                    .unwrap()
+                    .find(|d| d.name().map(|n| &n == sel).unwrap_or(false))
+                    .unwrap_or_else(|| panic!("no output device named '{}'", sel))
+            }
+        }
+    }
+}
+
+/// Lists every host and its output devices with their supported config
+/// ranges, for `--list-devices`. `--device` indices/names are only
+/// meaningful within the host selected by `--host` (or the default host),
+/// since device indices restart at 0 for each host.
+fn list_devices() {
+    for host_id in cpal::available_hosts() {
+        let host = cpal::host_from_id(host_id).unwrap();
+        println!("host: {}", host_id.name());
+
+        for (i, device) in host.output_devices().unwrap().enumerate() {
+            println!("  [{}] {}", i, device.name().unwrap_or_default());
+
+            for cnf in device.supported_output_configs().unwrap() {
+                println!(
+                    "        channels={} rate={}..{}Hz format={:?}",
+                    cnf.channels(),
+                    cnf.min_sample_rate().0,
+                    cnf.max_sample_rate().0,
+                    cnf.sample_format(),
+                );
+            }
+        }
+    }
+}
+
+/// Builds the cpal output stream for one sample format. Sizes the ring to
+/// a few callback-lengths the first time the device's actual frame size is
+/// seen, and again whenever that frame size changes.
+fn build_stream<T: cpal::Sample>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    channels: usize,
+    ratio: f64,
+    ring: Arc<Mutex<RingBuffer>>,
+) -> Result<cpal::Stream, cpal::BuildStreamError> {
+    let mut pos = 0f64;
+    let mut sized_for = 0usize;
+
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            let device_frames = data.len() / channels;
+            if device_frames != sized_for {
+                let capacity = (RING_CALLBACKS as f64 * device_frames as f64 * ratio).ceil() as usize;
+                ring.lock().unwrap().resize(capacity.max(1));
+                sized_for = device_frames;
+            }
+            play_frame(data, &ring, channels, ratio, &mut pos)
+        },
         |err| error!("error while playing audio: {}", err),
-    ).unwrap();
+    )
+}
+
+fn create_stream(ring: &Arc<Mutex<RingBuffer>>, host: &Option<String>, device: &Option<String>, sample_format: Option<cpal::SampleFormat>) -> cpal::Stream {
+    let host = find_host(host);
+    let device = find_device(&host, device);
+    info!("using host '{}', output device: {}", host.id().name(), device.name().unwrap_or_default());
+    let supported_config = select_config(&device, sample_format);
+    let sample_format = supported_config.sample_format();
+    let config = supported_config.config();
+    let channels = config.channels as usize;
+    let ratio = AUDIO_SAMPLE_RATE as f64 / config.sample_rate.0 as f64;
+
+    let stream = match sample_format {
+        cpal::SampleFormat::F32 => build_stream::<f32>(&device, &config, channels, ratio, ring.clone()),
+        cpal::SampleFormat::I16 => build_stream::<i16>(&device, &config, channels, ratio, ring.clone()),
+        cpal::SampleFormat::U16 => build_stream::<u16>(&device, &config, channels, ratio, ring.clone()),
+    }.unwrap();
 
     stream.play().unwrap();
 
@@ -47,16 +200,17 @@ fn create_stream(sample_buf: &Arc<Mutex<Vec<f32>>>) -> cpal::Stream {
 }
 
 pub struct AudioPlayer {
-    /// A dynamic buffer of samples
-    sample_buf: Arc<Mutex<Vec<f32>>>,
+    /// Ring buffer of frames produced by the emulator, consumed by the
+    /// cpal callback
+    sample_buf: Arc<Mutex<RingBuffer>>,
     /// Keep the stream alive
     pub stream: cpal::Stream,
 }
 
 impl AudioPlayer {
-    pub fn new() -> Self {
-        let sample_buf = Arc::new(Mutex::new(Vec::new()));
-        let stream = create_stream(&sample_buf);
+    pub fn new(host: &Option<String>, device: &Option<String>, sample_format: Option<cpal::SampleFormat>) -> Self {
+        let sample_buf = Arc::new(Mutex::new(RingBuffer::new(0)));
+        let stream = create_stream(&sample_buf, host, device, sample_format);
 
         Self {
             sample_buf,
@@ -67,13 +221,7 @@ impl AudioPlayer {
 
 impl AudioSpeaker for AudioPlayer {
     fn set_samples(&mut self, left: f32, right: f32) {
-        let mut sample_buf = self.sample_buf.lock().unwrap();
-        let max_len = ((AUDIO_SAMPLE_RATE * 300) / 1000) as usize;
-        // stop if the buffer has more than 300ms of samples
-        if sample_buf.len() < max_len {
-            sample_buf.push(left);
-            sample_buf.push(right);
-        }
+        self.sample_buf.lock().unwrap().push(left, right);
     }
 }
 
@@ -137,12 +285,67 @@ impl SerialOutput for SerialConsole {
     }
 }
 
+/// Parsed command-line invocation: the ROM path plus the optional
+/// `--host`/`--device`/`--sample-format` output selection.
+struct Args {
+    rom_path: String,
+    host: Option<String>,
+    device: Option<String>,
+    sample_format: Option<cpal::SampleFormat>,
+}
+
+/// Parses argv, handling `--list-devices` (which prints and exits),
+/// `--host <name>`, `--device <name-or-index>` and
+/// `--sample-format <f32|i16|u16>`. `--device` is resolved within `--host`
+/// (or the default host if unset), matching how `--list-devices` nests
+/// device indices under each host.
+fn parse_args(raw: &[String]) -> Args {
+    let mut host = None;
+    let mut device = None;
+    let mut sample_format = None;
+    let mut rom_path = None;
+    let mut it = raw.iter().skip(1);
+
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--list-devices" => {
+                list_devices();
+                std::process::exit(0);
+            }
+            "--host" => {
+                host = Some(it.next().expect("--host requires a value").clone());
+            }
+            "--device" => {
+                device = Some(it.next().expect("--device requires a value").clone());
+            }
+            "--sample-format" => {
+                let fmt = it.next().expect("--sample-format requires a value");
+                sample_format = Some(match fmt.as_str() {
+                    "f32" => cpal::SampleFormat::F32,
+                    "i16" => cpal::SampleFormat::I16,
+                    "u16" => cpal::SampleFormat::U16,
+                    other => panic!("unknown sample format '{}', expected f32/i16/u16", other),
+                });
+            }
+            other => rom_path = Some(other.to_owned()),
+        }
+    }
+
+    Args {
+        rom_path: rom_path.expect("missing ROM path"),
+        host,
+        device,
+        sample_format,
+    }
+}
+
 fn main() {
-    let args: Vec<String> = env::args().collect();
     env_logger::builder()
         .format_timestamp(None)
         .init();
-    let f: Vec<u8> = std::fs::read(&args[1]).unwrap();
+    let args: Vec<String> = env::args().collect();
+    let args = parse_args(&args);
+    let f: Vec<u8> = std::fs::read(&args.rom_path).unwrap();
 
     let rom = Rom::load(f).unwrap();
 
@@ -152,7 +355,8 @@ fn main() {
     let mut emu = System::new(rom,
                               Lcd::new(title),
                               SerialConsole::new("/tmp/padme_serial.log"),
-                              AudioPlayer::new());
+                              AudioPlayer::new(&args.host, &args.device, args.sample_format));
+    let mut gamepad = gamepad::GamepadInput::new(gamepad::GamepadMapping::default());
 
     emu.set_frame_rate(60);
 
@@ -161,14 +365,16 @@ fn main() {
 
         emu.update_frame();
 
-        let a_pressed = emu.screen().win.is_key_down(Key::A);
-        let b_pressed = emu.screen().win.is_key_down(Key::S);
-        let start_pressed = emu.screen().win.is_key_down(Key::Enter);
-        let select_pressed = emu.screen().win.is_key_down(Key::Tab);
-        let up_pressed = emu.screen().win.is_key_down(Key::Up);
-        let down_pressed = emu.screen().win.is_key_down(Key::Down);
-        let left_pressed = emu.screen().win.is_key_down(Key::Left);
-        let right_pressed = emu.screen().win.is_key_down(Key::Right);
+        let pad = gamepad.poll();
+
+        let a_pressed = emu.screen().win.is_key_down(Key::A) || pad.a;
+        let b_pressed = emu.screen().win.is_key_down(Key::S) || pad.b;
+        let start_pressed = emu.screen().win.is_key_down(Key::Enter) || pad.start;
+        let select_pressed = emu.screen().win.is_key_down(Key::Tab) || pad.select;
+        let up_pressed = emu.screen().win.is_key_down(Key::Up) || pad.up;
+        let down_pressed = emu.screen().win.is_key_down(Key::Down) || pad.down;
+        let left_pressed = emu.screen().win.is_key_down(Key::Left) || pad.left;
+        let right_pressed = emu.screen().win.is_key_down(Key::Right) || pad.right;
 
         emu.set_button(Button::A, a_pressed);
         emu.set_button(Button::B, b_pressed);
@@ -0,0 +1,85 @@
+/// Fixed-capacity circular buffer of interleaved stereo frames.
+///
+/// Used to hand audio frames from the emulator thread to the cpal
+/// callback without reallocating on every callback. When the
+/// producer outpaces the consumer, `push` overwrites the oldest frame
+/// instead of dropping unconditionally; when the consumer outpaces the
+/// producer, `peek` repeats the last available frame instead of reading
+/// uninitialized data.
+pub struct RingBuffer {
+    buf: Vec<f32>,
+    inp: usize,
+    out: usize,
+    len: usize,
+    capacity: usize,
+    /// Last frame pushed, repeated by `peek` on a full underrun.
+    last: (f32, f32),
+}
+
+impl RingBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            buf: vec![0.0; capacity * 2],
+            inp: 0,
+            out: 0,
+            len: 0,
+            capacity,
+            last: (0.0, 0.0),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn clear(&mut self) {
+        self.inp = 0;
+        self.out = 0;
+        self.len = 0;
+    }
+
+    /// Reinitializes the buffer for a new frame capacity, e.g. when the
+    /// device callback's frame size changes.
+    pub fn resize(&mut self, new_capacity: usize) {
+        self.buf = vec![0.0; new_capacity * 2];
+        self.capacity = new_capacity;
+        self.clear();
+    }
+
+    /// Pushes one stereo frame, overwriting the oldest frame once full.
+    pub fn push(&mut self, left: f32, right: f32) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.buf[self.inp * 2] = left;
+        self.buf[self.inp * 2 + 1] = right;
+        self.inp = (self.inp + 1) % self.capacity;
+        self.last = (left, right);
+
+        if self.len < self.capacity {
+            self.len += 1;
+        } else {
+            self.out = (self.out + 1) % self.capacity;
+        }
+    }
+
+    /// Reads the frame `offset` positions ahead of the read cursor, clamped
+    /// to the last available frame, or repeating the last frame ever
+    /// pushed (silence if none was) on a full underrun.
+    pub fn peek(&self, offset: usize) -> (f32, f32) {
+        if self.len == 0 {
+            return self.last;
+        }
+
+        let idx = (self.out + offset.min(self.len - 1)) % self.capacity;
+        (self.buf[idx * 2], self.buf[idx * 2 + 1])
+    }
+
+    /// Advances the read cursor by up to `n` frames.
+    pub fn advance(&mut self, n: usize) {
+        let n = n.min(self.len);
+        self.out = (self.out + n) % self.capacity;
+        self.len -= n;
+    }
+}